@@ -0,0 +1,24 @@
+// Embed the git commit and build time so `claude-launcher --version` can tell
+// a `cargo install`ed binary from a local build
+use std::process::Command;
+
+fn main() {
+    let sha = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|sha| !sha.is_empty());
+    // Without git (e.g. a crates.io source package) the binary reports "unknown"
+    if let Some(sha) = sha {
+        println!("cargo:rustc-env=CLAUDE_LAUNCHER_GIT_SHA={}", sha);
+    }
+
+    println!(
+        "cargo:rustc-env=CLAUDE_LAUNCHER_BUILD_DATE={}",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+}
@@ -4,7 +4,8 @@
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -32,10 +33,63 @@ pub enum WorktreeError {
 
     #[error("Uncommitted changes in worktree")]
     UncommittedChanges,
+
+    #[error(
+        "Worktree limit reached ({0}). Run 'claude-launcher --cleanup-worktrees' to remove old worktrees"
+    )]
+    LimitReached(usize),
 }
 
 type Result<T> = std::result::Result<T, WorktreeError>;
 
+pub const DEFAULT_BRANCH_PREFIX: &str = "claude-phase-";
+pub const DEFAULT_NAMING_PATTERN: &str = "claude-phase-{id}-{timestamp}";
+pub const DEFAULT_PARENT_DIR: &str = "../";
+
+static TRACE_GIT: AtomicBool = AtomicBool::new(false);
+
+// Echo every git invocation and its exit status to stderr (--trace-git)
+pub fn set_trace_git(enabled: bool) {
+    TRACE_GIT.store(enabled, Ordering::Relaxed);
+}
+
+// Central wrapper for every git command the launcher runs
+fn run_git(dir: Option<&Path>, args: &[&str]) -> std::io::Result<Output> {
+    let trace = TRACE_GIT.load(Ordering::Relaxed);
+    if trace {
+        match dir {
+            Some(dir) => eprintln!("+ git {} (in {})", args.join(" "), dir.display()),
+            None => eprintln!("+ git {}", args.join(" ")),
+        }
+    }
+
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command.args(args).output();
+
+    if trace {
+        match &output {
+            Ok(out) => match out.status.code() {
+                Some(code) => eprintln!("  -> exit {}", code),
+                None => eprintln!("  -> terminated by signal"),
+            },
+            Err(e) => eprintln!("  -> failed to start: {}", e),
+        }
+    }
+
+    output
+}
+
+pub fn git(args: &[&str]) -> std::io::Result<Output> {
+    run_git(None, args)
+}
+
+pub fn git_in(dir: &Path, args: &[&str]) -> std::io::Result<Output> {
+    run_git(Some(dir), args)
+}
+
 #[derive(Debug, Clone)]
 pub struct Worktree {
     pub name: String,
@@ -46,9 +100,17 @@ pub struct Worktree {
 
 impl Worktree {
     pub fn new(phase_id: &str) -> Self {
+        Self::from_pattern(DEFAULT_NAMING_PATTERN, phase_id, DEFAULT_PARENT_DIR)
+    }
+
+    // Name the worktree and its branch from a pattern with {id} and {timestamp},
+    // placing it under `parent_dir` (relative to the repo or absolute)
+    pub fn from_pattern(pattern: &str, phase_id: &str, parent_dir: &str) -> Self {
         let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let name = format!("claude-phase-{}-{}", phase_id, timestamp);
-        let path = PathBuf::from(format!("../{}", name));
+        let name = pattern
+            .replace("{id}", phase_id)
+            .replace("{timestamp}", &timestamp);
+        let path = Path::new(parent_dir).join(&name);
         Self {
             name: name.clone(),
             path,
@@ -58,11 +120,78 @@ impl Worktree {
     }
 }
 
+// Placeholders a naming pattern may use; anything else in braces is a typo
+pub const NAMING_PATTERN_TOKENS: [&str; 2] = ["{id}", "{timestamp}"];
+
+// Reject a naming pattern with unknown `{...}` tokens, without a literal
+// prefix and {timestamp}, or whose branch names git would refuse, before any
+// worktree is created from it
+pub fn validate_naming_pattern(pattern: &str) -> Result<()> {
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let token = match rest[start..].find('}') {
+            Some(end) => &rest[start..=start + end],
+            None => &rest[start..],
+        };
+        if !NAMING_PATTERN_TOKENS.contains(&token) {
+            return Err(WorktreeError::ConfigError(format!(
+                "naming_pattern \"{}\" has unknown token {}; valid tokens are {}",
+                pattern,
+                token,
+                NAMING_PATTERN_TOKENS.join(" and ")
+            )));
+        }
+        rest = &rest[start + token.len()..];
+    }
+    // Listing and cleanup recognize launcher branches by their literal
+    // prefix and timestamp; without both, the user's own branches would match
+    if pattern.starts_with('{') {
+        return Err(WorktreeError::ConfigError(format!(
+            "naming_pattern \"{}\" must start with literal text, e.g. \"{}\"",
+            pattern, DEFAULT_NAMING_PATTERN
+        )));
+    }
+    if !pattern.contains("{timestamp}") {
+        return Err(WorktreeError::ConfigError(format!(
+            "naming_pattern \"{}\" must contain {{timestamp}}",
+            pattern
+        )));
+    }
+
+    let branch = pattern
+        .replace("{id}", "1")
+        .replace("{timestamp}", "20250101_120000");
+    if !is_valid_branch_name(&branch) {
+        return Err(WorktreeError::ConfigError(format!(
+            "naming_pattern \"{}\" gives branch names like \"{}\", which git rejects",
+            pattern, branch
+        )));
+    }
+    Ok(())
+}
+
+// The rules of `git check-ref-format --branch`, without running git
+pub fn is_valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "@"
+        && !name.starts_with('-')
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && !name
+            .chars()
+            .any(|c| c.is_ascii_control() || " ~^:?*[\\".contains(c))
+        && name
+            .split('/')
+            .all(|part| !part.starts_with('.') && !part.ends_with(".lock"))
+}
+
 // Add validation functions
 pub fn validate_git_repo() -> Result<()> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--git-dir"])
-        .output()?;
+    let output = git(&["rev-parse", "--git-dir"])?;
 
     if !output.status.success() {
         return Err(WorktreeError::NotInGitRepo);
@@ -72,10 +201,17 @@ pub fn validate_git_repo() -> Result<()> {
 }
 
 pub fn check_uncommitted_changes(path: &Path) -> Result<()> {
-    let output = Command::new("git")
-        .current_dir(path)
-        .args(["status", "--porcelain"])
-        .output()?;
+    if !uncommitted_paths(path)?.is_empty() {
+        return Err(WorktreeError::UncommittedChanges);
+    }
+
+    Ok(())
+}
+
+// Paths with uncommitted changes (including untracked files), relative to
+// the repository root. For renames this is the new path
+pub fn uncommitted_paths(path: &Path) -> Result<Vec<String>> {
+    let output = git_in(path, &["status", "--porcelain"])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(
@@ -83,23 +219,55 @@ pub fn check_uncommitted_changes(path: &Path) -> Result<()> {
         ));
     }
 
-    let status = String::from_utf8_lossy(&output.stdout);
-    if !status.trim().is_empty() {
-        return Err(WorktreeError::UncommittedChanges);
-    }
-
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(|entry| match entry.split_once(" -> ") {
+            Some((_, renamed)) => renamed.to_string(),
+            None => entry.to_string(),
+        })
+        .collect())
 }
 
 // Enhanced create_worktree with validation
 pub fn create_worktree(phase_id: &str, base_branch: &str) -> Result<Worktree> {
+    create_worktree_from_pattern(
+        phase_id,
+        base_branch,
+        DEFAULT_NAMING_PATTERN,
+        DEFAULT_PARENT_DIR,
+    )
+}
+
+// Create a worktree named after the configured naming pattern
+pub fn create_worktree_with_config(
+    phase_id: &str,
+    config: &crate::WorktreeConfig,
+) -> Result<Worktree> {
+    // Enforce the limit up front instead of relying on cleanup afterwards
+    if list_worktrees_with_config(config)?.len() >= config.max_worktrees {
+        return Err(WorktreeError::LimitReached(config.max_worktrees));
+    }
+
+    create_worktree_from_pattern(
+        phase_id,
+        &config.base_branch(),
+        &config.branch_pattern(),
+        &config.parent_dir,
+    )
+}
+
+fn create_worktree_from_pattern(
+    phase_id: &str,
+    base_branch: &str,
+    pattern: &str,
+    parent_dir: &str,
+) -> Result<Worktree> {
     // Validate we're in a git repo
     validate_git_repo()?;
 
     // Check if base branch exists
-    let output = Command::new("git")
-        .args(["rev-parse", "--verify", base_branch])
-        .output()?;
+    let output = git(&["rev-parse", "--verify", base_branch])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(format!(
@@ -107,7 +275,7 @@ pub fn create_worktree(phase_id: &str, base_branch: &str) -> Result<Worktree> {
             base_branch
         )));
     }
-    let mut worktree = Worktree::new(phase_id);
+    let mut worktree = Worktree::from_pattern(pattern, phase_id, parent_dir);
 
     // Check if worktree already exists
     if worktree.path.exists() {
@@ -115,9 +283,7 @@ pub fn create_worktree(phase_id: &str, base_branch: &str) -> Result<Worktree> {
     }
 
     // Check if branch already exists
-    let branch_check = Command::new("git")
-        .args(["rev-parse", "--verify", &worktree.branch])
-        .output()?;
+    let branch_check = git(&["rev-parse", "--verify", &worktree.branch])?;
 
     if branch_check.status.success() {
         // Branch exists, use a different name
@@ -133,16 +299,14 @@ pub fn create_worktree(phase_id: &str, base_branch: &str) -> Result<Worktree> {
     }
 
     // Create worktree with new branch
-    let output = Command::new("git")
-        .args([
-            "worktree",
-            "add",
-            "-b",
-            &worktree.branch,
-            worktree.path.to_str().unwrap(),
-            base_branch,
-        ])
-        .output()?;
+    let output = git(&[
+        "worktree",
+        "add",
+        "-b",
+        &worktree.branch,
+        worktree.path.to_str().unwrap(),
+        base_branch,
+    ])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(format!(
@@ -171,14 +335,12 @@ pub fn remove_worktree(worktree_name: &str) -> Result<()> {
     }
 
     // Remove worktree
-    let output = Command::new("git")
-        .args([
-            "worktree",
-            "remove",
-            worktree.path.to_str().unwrap(),
-            "--force",
-        ])
-        .output()?;
+    let output = git(&[
+        "worktree",
+        "remove",
+        worktree.path.to_str().unwrap(),
+        "--force",
+    ])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(format!(
@@ -188,21 +350,17 @@ pub fn remove_worktree(worktree_name: &str) -> Result<()> {
     }
 
     // Delete the branch if it exists
-    let _ = Command::new("git")
-        .args(["branch", "-D", &worktree.branch])
-        .output();
+    let _ = git(&["branch", "-D", &worktree.branch]);
 
     // Prune worktree refs
-    Command::new("git").args(["worktree", "prune"]).output()?;
+    git(&["worktree", "prune"])?;
 
     Ok(())
 }
 
 // Helper function to list all worktrees
 pub fn list_all_worktrees() -> Result<Vec<Worktree>> {
-    let output = Command::new("git")
-        .args(["worktree", "list", "--porcelain"])
-        .output()?;
+    let output = git(&["worktree", "list", "--porcelain"])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(
@@ -210,50 +368,154 @@ pub fn list_all_worktrees() -> Result<Vec<Worktree>> {
         ));
     }
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
+    let state = WorktreeState::load().unwrap_or_else(|_| WorktreeState::new());
+    Ok(parse_worktree_list(
+        &String::from_utf8_lossy(&output.stdout),
+        &state,
+    ))
+}
+
+// Parse `git worktree list --porcelain`. A detached worktree (e.g. after an
+// interrupted rebase) is named after its directory, with the short commit as
+// its branch
+fn parse_worktree_list(porcelain: &str, state: &WorktreeState) -> Vec<Worktree> {
     let mut worktrees = Vec::new();
 
-    // Parse worktree list output
-    let mut current_path = None;
-    let mut current_branch = None;
-
-    for line in output_str.lines() {
-        if line.starts_with("worktree ") {
-            current_path = Some(line.trim_start_matches("worktree ").to_string());
-        } else if line.starts_with("branch refs/heads/") {
-            current_branch = Some(line.trim_start_matches("branch refs/heads/").to_string());
-
-            if let (Some(path), Some(branch)) = (&current_path, &current_branch) {
-                let parts: Vec<&str> = branch.split('-').collect();
-                let timestamp = if parts.len() >= 4 && branch.starts_with("claude-phase-") {
-                    parts[3..].join("-")
-                } else {
-                    "unknown".to_string()
-                };
-                worktrees.push(Worktree {
-                    name: branch.clone(),
-                    path: PathBuf::from(path),
-                    branch: branch.clone(),
-                    created_at: timestamp,
-                });
+    for entry in porcelain.split("\n\n") {
+        let mut path = None;
+        let mut head = None;
+        let mut branch = None;
+        let mut detached = false;
+        for line in entry.lines() {
+            if let Some(value) = line.strip_prefix("worktree ") {
+                path = Some(PathBuf::from(value));
+            } else if let Some(value) = line.strip_prefix("HEAD ") {
+                head = Some(value);
+            } else if let Some(value) = line.strip_prefix("branch refs/heads/") {
+                branch = Some(value.to_string());
+            } else if line == "detached" {
+                detached = true;
+            }
+        }
+
+        let Some(path) = path else {
+            continue;
+        };
+        let (name, branch) = match (branch, head) {
+            (Some(branch), _) => (branch.clone(), branch),
+            (None, Some(head)) if detached => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| head.to_string());
+                (name, head.chars().take(7).collect())
             }
+            _ => continue,
+        };
+        worktrees.push(Worktree {
+            created_at: worktree_created_at(state, &name, &path),
+            name,
+            path,
+            branch,
+        });
+    }
+
+    worktrees
+}
+
+// Creation time recorded in the worktree state, falling back to the
+// directory's modification time for worktrees the state doesn't know about
+fn worktree_created_at(state: &WorktreeState, name: &str, path: &Path) -> String {
+    if let Some(active) = state
+        .active_worktrees
+        .iter()
+        .find(|w| w.worktree_name == name)
+    {
+        return active.created_at.clone();
+    }
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            chrono::DateTime::<Local>::from(modified)
+                .format("%Y%m%d_%H%M%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+// Whether `branch` could have been produced by `pattern` (possibly with the
+// "-retry" suffix create_worktree adds on a name clash). {id} stands for any
+// non-empty text and {timestamp} for a %Y%m%d_%H%M%S stamp; a pattern without
+// {timestamp} matches nothing, as it can't tell launcher branches from the
+// user's own
+pub fn branch_matches_pattern(branch: &str, pattern: &str) -> bool {
+    if !pattern.contains("{timestamp}") {
+        return false;
+    }
+    matches_from(branch, pattern)
+        || branch
+            .strip_suffix("-retry")
+            .is_some_and(|branch| matches_from(branch, pattern))
+}
+
+fn matches_from(branch: &str, pattern: &str) -> bool {
+    if let Some(pattern) = pattern.strip_prefix("{timestamp}") {
+        return branch.get(..15).is_some_and(is_timestamp) && matches_from(&branch[15..], pattern);
+    }
+    if let Some(pattern) = pattern.strip_prefix("{id}") {
+        return branch
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .any(|end| matches_from(&branch[end..], pattern));
+    }
+    match (pattern.chars().next(), branch.chars().next()) {
+        (None, _) => branch.is_empty(),
+        (Some(p), Some(b)) if p == b => {
+            matches_from(&branch[b.len_utf8()..], &pattern[p.len_utf8()..])
         }
+        _ => false,
     }
+}
 
-    Ok(worktrees)
+// The shape Worktree::from_pattern gives {timestamp}: 20250101_120000
+fn is_timestamp(text: &str) -> bool {
+    text.bytes().enumerate().all(|(i, b)| match i {
+        8 => b == b'_',
+        _ => b.is_ascii_digit(),
+    })
 }
 
 pub fn list_claude_worktrees() -> Result<Vec<Worktree>> {
+    list_managed_worktrees(DEFAULT_BRANCH_PREFIX, DEFAULT_NAMING_PATTERN)
+}
+
+// Worktrees the launcher created: the branch carries the configured prefix
+// or matches the naming pattern
+pub fn list_managed_worktrees(prefix: &str, pattern: &str) -> Result<Vec<Worktree>> {
     let all_worktrees = list_all_worktrees()?;
     Ok(all_worktrees
         .into_iter()
-        .filter(|w| w.branch.starts_with("claude-phase-"))
+        .filter(|w| {
+            // Detached worktrees are only recognizable by their name
+            [&w.branch, &w.name].iter().any(|n| {
+                (!prefix.is_empty() && n.starts_with(prefix)) || branch_matches_pattern(n, pattern)
+            })
+        })
         .collect())
 }
 
+pub fn list_worktrees_with_config(config: &crate::WorktreeConfig) -> Result<Vec<Worktree>> {
+    list_managed_worktrees(&config.branch_prefix, &config.branch_pattern())
+}
+
 pub fn cleanup_old_worktrees(max_worktrees: usize) -> Result<()> {
-    let mut worktrees = list_claude_worktrees()?;
+    cleanup_managed_worktrees(
+        max_worktrees,
+        list_managed_worktrees(DEFAULT_BRANCH_PREFIX, DEFAULT_NAMING_PATTERN)?,
+    )
+}
 
+fn cleanup_managed_worktrees(max_worktrees: usize, mut worktrees: Vec<Worktree>) -> Result<()> {
     if worktrees.len() <= max_worktrees {
         return Ok(());
     }
@@ -285,6 +547,26 @@ pub struct ActiveWorktree {
     pub status: WorktreeStatus,
 }
 
+impl ActiveWorktree {
+    pub fn worktree(&self) -> Worktree {
+        Worktree {
+            name: self.worktree_name.clone(),
+            path: self.worktree_path.clone(),
+            branch: self.worktree_name.clone(),
+            created_at: self.created_at.clone(),
+        }
+    }
+}
+
+// Where worktree_state.json and the worktrees on disk disagree
+#[derive(Debug, Default)]
+pub struct StateMismatches {
+    // State entries whose directory no longer exists
+    pub orphaned: Vec<ActiveWorktree>,
+    // Worktrees on disk that no state entry refers to
+    pub untracked: Vec<Worktree>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum WorktreeStatus {
     Active,
@@ -346,6 +628,54 @@ impl WorktreeState {
         }
     }
 
+    // Flip a phase's most recent worktree back to Active if it Failed and its
+    // directory is still there
+    pub fn reactivate_failed(&mut self, phase_id: &str) -> Option<&ActiveWorktree> {
+        let wt = self
+            .active_worktrees
+            .iter_mut()
+            .rev()
+            .find(|w| w.phase_id == phase_id)?;
+        if wt.status != WorktreeStatus::Failed || !wt.worktree_path.exists() {
+            return None;
+        }
+        wt.status = WorktreeStatus::Active;
+        Some(wt)
+    }
+
+    // Drop entries whose worktree directory no longer exists, returning them
+    pub fn prune_missing(&mut self) -> Vec<ActiveWorktree> {
+        let (kept, missing) = self
+            .active_worktrees
+            .drain(..)
+            .partition(|w| w.worktree_path.exists());
+        self.active_worktrees = kept;
+        missing
+    }
+
+    // Compare the state against `on_disk` (e.g. the managed worktrees git
+    // lists) without changing either
+    pub fn mismatches(&self, on_disk: &[Worktree]) -> StateMismatches {
+        StateMismatches {
+            orphaned: self
+                .active_worktrees
+                .iter()
+                .filter(|w| !w.worktree_path.exists())
+                .cloned()
+                .collect(),
+            untracked: on_disk
+                .iter()
+                .filter(|w| {
+                    !self
+                        .active_worktrees
+                        .iter()
+                        .any(|active| active.worktree_name == w.name)
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
     pub fn get_active_worktree(&self, phase_id: &str) -> Option<&ActiveWorktree> {
         self.active_worktrees
             .iter()
@@ -376,13 +706,15 @@ impl WorktreeState {
 
         // Apply max worktrees limit
         if config.auto_cleanup {
-            match cleanup_old_worktrees(config.max_worktrees) {
+            let cleanup = list_worktrees_with_config(config)
+                .and_then(|worktrees| cleanup_managed_worktrees(config.max_worktrees, worktrees));
+            match cleanup {
                 Ok(_) => {}
                 Err(e) => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to cleanup old worktrees: {}", e),
-                    ));
+                    return Err(std::io::Error::other(format!(
+                        "Failed to cleanup old worktrees: {}",
+                        e
+                    )));
                 }
             }
         }
@@ -394,32 +726,41 @@ impl WorktreeState {
 
 // Add recovery function for orphaned worktrees
 pub fn recover_orphaned_worktrees() -> Result<Vec<String>> {
+    let recovered = orphaned_worktrees()?;
+
+    // Actually prune if we found orphaned worktrees
+    if !recovered.is_empty() {
+        git(&["worktree", "prune"])?;
+    }
+
+    Ok(recovered)
+}
+
+// Worktrees `git worktree prune` would remove, without pruning them
+pub fn orphaned_worktrees() -> Result<Vec<String>> {
     validate_git_repo()?;
 
-    let mut recovered = Vec::new();
+    let mut orphaned = Vec::new();
 
     // Run worktree prune in dry-run mode to find orphaned worktrees
-    let output = Command::new("git")
-        .args(["worktree", "prune", "--dry-run", "-v"])
-        .output()?;
+    let output = git(&["worktree", "prune", "--dry-run", "-v"])?;
 
     if output.status.success() {
-        let output_str = String::from_utf8_lossy(&output.stdout);
+        // git reports "Removing worktrees/<name>: <reason>" on stderr
+        let output_str = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
         for line in output_str.lines() {
-            if line.contains("Removing worktrees") {
-                if let Some(path) = line.split("Removing worktrees/").nth(1) {
-                    recovered.push(path.trim_end_matches(':').to_string());
-                }
+            if let Some(rest) = line.split("Removing worktrees/").nth(1) {
+                let name = rest.split(':').next().unwrap_or(rest);
+                orphaned.push(name.to_string());
             }
         }
-
-        // Actually prune if we found orphaned worktrees
-        if !recovered.is_empty() {
-            Command::new("git").args(["worktree", "prune"]).output()?;
-        }
     }
 
-    Ok(recovered)
+    Ok(orphaned)
 }
 
 // Add function to safely sync worktree changes
@@ -432,53 +773,114 @@ pub fn sync_worktree_safely(worktree: &Worktree, target_branch: &str) -> Result<
     }
 
     // Fetch latest changes
-    Command::new("git")
-        .current_dir(&worktree.path)
-        .args(["fetch", "origin"])
-        .output()?;
+    let fetch = git_in(&worktree.path, &["fetch", "origin"])?;
+    if !fetch.status.success() {
+        return Err(WorktreeError::GitError(format!(
+            "Cannot fetch origin: {}",
+            String::from_utf8_lossy(&fetch.stderr).trim()
+        )));
+    }
 
     // Check if we can fast-forward merge
-    let merge_base = Command::new("git")
-        .current_dir(&worktree.path)
-        .args([
+    let merge_base = git_in(
+        &worktree.path,
+        &[
             "merge-base",
             &worktree.branch,
             &format!("origin/{}", target_branch),
-        ])
-        .output()?;
+        ],
+    )?;
 
     if !merge_base.status.success() {
-        return Err(WorktreeError::GitError(
-            "Cannot determine merge base".to_string(),
-        ));
+        return Err(WorktreeError::GitError(format!(
+            "Cannot determine merge base with origin/{}",
+            target_branch
+        )));
     }
 
     // Attempt rebase to keep history clean
-    let rebase = Command::new("git")
-        .current_dir(&worktree.path)
-        .args(["rebase", &format!("origin/{}", target_branch)])
-        .output()?;
+    let rebase = git_in(
+        &worktree.path,
+        &["rebase", &format!("origin/{}", target_branch)],
+    )?;
 
     if !rebase.status.success() {
         // Abort rebase if it failed
-        Command::new("git")
-            .current_dir(&worktree.path)
-            .args(["rebase", "--abort"])
-            .output()?;
-
-        return Err(WorktreeError::GitError(
-            "Cannot rebase worktree changes".to_string(),
-        ));
+        git_in(&worktree.path, &["rebase", "--abort"])?;
+
+        // git reports conflicts on stdout and other failures on stderr
+        let stdout = String::from_utf8_lossy(&rebase.stdout);
+        let stderr = String::from_utf8_lossy(&rebase.stderr);
+        let reason = stdout
+            .lines()
+            .chain(stderr.lines())
+            .find(|line| line.starts_with("CONFLICT") || line.starts_with("error:"))
+            .unwrap_or_else(|| stderr.trim());
+        return Err(WorktreeError::GitError(format!(
+            "Cannot rebase worktree changes onto origin/{}, rebase aborted: {}",
+            target_branch, reason
+        )));
     }
 
     Ok(())
 }
 
+// Files that would conflict if `branch` were merged into `base_branch`,
+// found with `git merge-tree` without touching either branch or the index
+pub fn merge_conflicts(base_branch: &str, branch: &str) -> Result<Vec<String>> {
+    let output = git(&[
+        "merge-tree",
+        "--write-tree",
+        "--name-only",
+        "--no-messages",
+        base_branch,
+        branch,
+    ])?;
+
+    match output.status.code() {
+        Some(0) => Ok(Vec::new()),
+        // The first line is the merged tree, then one line per conflicted
+        // file (repeated when a file has several conflicting stages)
+        Some(1) => {
+            let mut files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .skip(1)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            files.dedup();
+            Ok(files)
+        }
+        _ => Err(WorktreeError::GitError(format!(
+            "Failed to check {} for merge conflicts with {}: {}",
+            branch,
+            base_branch,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+// The branch worktrees start from when worktree.base_branch is not set:
+// origin's default branch, else the branch checked out now, else "main"
+pub fn default_branch() -> String {
+    if let Ok(output) = git(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"]) {
+        let origin_head = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if let Some(branch) = origin_head.strip_prefix("origin/") {
+            if output.status.success() && !branch.is_empty() {
+                return branch.to_string();
+            }
+        }
+    }
+
+    match get_current_branch() {
+        Ok(branch) if !branch.is_empty() && branch != "HEAD" => branch,
+        _ => "main".to_string(),
+    }
+}
+
 // Helper function to get current git branch
 pub fn get_current_branch() -> Result<String> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
-        .output()?;
+    let output = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
 
     if !output.status.success() {
         return Err(WorktreeError::GitError(
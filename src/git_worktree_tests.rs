@@ -12,7 +12,20 @@ fn check_git_available() -> bool {
         .unwrap_or(false)
 }
 
-fn setup_test_repo() -> Option<TempDir> {
+// The repo lives one level down so worktrees created at "../<name>" land
+// inside the temp dir and are removed with it
+struct TestRepo {
+    _root: TempDir,
+    path: std::path::PathBuf,
+}
+
+impl TestRepo {
+    fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+fn setup_test_repo() -> Option<TestRepo> {
     if !check_git_available() {
         eprintln!("Git not available, skipping test");
         return None;
@@ -26,11 +39,16 @@ fn setup_test_repo() -> Option<TempDir> {
         }
     };
     
-    let repo_path = temp_dir.path();
+    let repo_path = temp_dir.path().join("repo");
+    if fs::create_dir(&repo_path).is_err() {
+        eprintln!("Failed to create repo dir");
+        return None;
+    }
+    let repo_path = repo_path.as_path();
 
     // Initialize git repo
     let output = std::process::Command::new("git")
-        .current_dir(&repo_path)
+        .current_dir(repo_path)
         .arg("init")
         .output();
         
@@ -47,33 +65,33 @@ fn setup_test_repo() -> Option<TempDir> {
     }
 
     // Configure git user (required for commits)
-    if std::process::Command::new("git")
-        .current_dir(&repo_path)
-        .args(&["config", "user.email", "test@example.com"])
+    if !std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["config", "user.email", "test@example.com"])
         .output()
         .map(|o| o.status.success())
-        .unwrap_or(false) == false {
+        .unwrap_or(false) {
             eprintln!("Failed to set git email");
             return None;
         }
 
-    if std::process::Command::new("git")
-        .current_dir(&repo_path)
-        .args(&["config", "user.name", "Test User"])
+    if !std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["config", "user.name", "Test User"])
         .output()
         .map(|o| o.status.success())
-        .unwrap_or(false) == false {
+        .unwrap_or(false) {
             eprintln!("Failed to set git name");
             return None;
         }
 
     // Set the default branch name to "main"
-    if std::process::Command::new("git")
-        .current_dir(&repo_path)
-        .args(&["checkout", "-b", "main"])
+    if !std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["checkout", "-b", "main"])
         .output()
         .map(|o| o.status.success())
-        .unwrap_or(false) == false {
+        .unwrap_or(false) {
             eprintln!("Failed to create main branch");
             return None;
         }
@@ -84,31 +102,35 @@ fn setup_test_repo() -> Option<TempDir> {
         return None;
     }
     
-    if std::process::Command::new("git")
-        .current_dir(&repo_path)
-        .args(&["add", "."])
+    if !std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["add", "."])
         .output()
         .map(|o| o.status.success())
-        .unwrap_or(false) == false {
+        .unwrap_or(false) {
             eprintln!("Failed to add files");
             return None;
         }
 
-    if std::process::Command::new("git")
-        .current_dir(&repo_path)
-        .args(&["commit", "-m", "Initial commit"])
+    if !std::process::Command::new("git")
+        .current_dir(repo_path)
+        .args(["commit", "-m", "Initial commit"])
         .output()
         .map(|o| o.status.success())
-        .unwrap_or(false) == false {
+        .unwrap_or(false) {
             eprintln!("Failed to commit");
             return None;
         }
 
-    Some(temp_dir)
+    Some(TestRepo {
+        path: repo_path.to_path_buf(),
+        _root: temp_dir,
+    })
 }
 
 #[test]
 fn test_worktree_creation() {
+    let _cwd = crate::lock_cwd();
     let Some(temp_dir) = setup_test_repo() else {
         return; // Skip test if git is not available
     };
@@ -143,6 +165,7 @@ fn test_worktree_creation() {
 
 #[test]
 fn test_worktree_listing() {
+    let _cwd = crate::lock_cwd();
     let Some(temp_dir) = setup_test_repo() else {
         return; // Skip test if git is not available
     };
@@ -174,8 +197,335 @@ fn test_worktree_listing() {
     let _ = std::env::set_current_dir(original_dir);
 }
 
+#[test]
+fn test_worktree_listing_with_custom_prefix() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Failed to get current directory: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = std::env::set_current_dir(temp_dir.path()) {
+        eprintln!("Failed to change to temp directory: {}", e);
+        return;
+    }
+
+    let mut config = crate::default_worktree_config();
+    config.branch_prefix = "acme-launcher-".to_string();
+
+    let worktree = create_worktree_with_config("1", &config).unwrap();
+    assert!(worktree.name.starts_with("acme-launcher-1-"));
+    fs::create_dir(".claude-launcher").unwrap();
+    let mut state = WorktreeState::new();
+    state.add_worktree("1".to_string(), &worktree);
+    state.save().unwrap();
+
+    // Only recognized under the configured prefix
+    let worktrees = list_worktrees_with_config(&config).unwrap();
+    assert_eq!(worktrees.len(), 1);
+    assert_eq!(worktrees[0].created_at, worktree.created_at);
+    assert!(list_claude_worktrees().unwrap().is_empty());
+
+    // Cleanup
+    let _ = std::env::set_current_dir(original_dir);
+}
+
+#[test]
+fn test_worktree_custom_naming_pattern() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Failed to get current directory: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = std::env::set_current_dir(temp_dir.path()) {
+        eprintln!("Failed to change to temp directory: {}", e);
+        return;
+    }
+
+    let mut config = crate::default_worktree_config();
+    config.naming_pattern = "feat-{id}-{timestamp}".to_string();
+
+    let worktree = create_worktree_with_config("3", &config).unwrap();
+    assert!(worktree.name.starts_with("feat-3-"));
+    assert_eq!(worktree.branch, worktree.name);
+
+    // Round-trips through listing even without the claude-phase- prefix
+    let worktrees = list_worktrees_with_config(&config).unwrap();
+    assert_eq!(worktrees.len(), 1);
+    assert_eq!(worktrees[0].branch, worktree.branch);
+    // Not in the worktree state, so the directory's mtime is reported
+    assert!(
+        chrono::NaiveDateTime::parse_from_str(&worktrees[0].created_at, "%Y%m%d_%H%M%S").is_ok()
+    );
+
+    // Cleanup
+    let _ = std::env::set_current_dir(original_dir);
+}
+
+#[test]
+fn test_worktree_created_at_with_dashed_phase_id() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    fs::create_dir(".claude-launcher").unwrap();
+
+    let mut worktree = create_worktree("test-phase-1", "main").unwrap();
+    let listed_from_mtime = list_claude_worktrees().unwrap();
+
+    worktree.created_at = "20240101_090000".to_string();
+    let mut state = WorktreeState::new();
+    state.add_worktree("test-phase-1".to_string(), &worktree);
+    state.save().unwrap();
+    let listed_from_state = list_claude_worktrees().unwrap();
+    let _ = std::env::set_current_dir(original_dir);
+
+    assert_eq!(listed_from_mtime.len(), 1);
+    assert!(chrono::NaiveDateTime::parse_from_str(
+        &listed_from_mtime[0].created_at,
+        "%Y%m%d_%H%M%S"
+    )
+    .is_ok());
+    assert_eq!(listed_from_state[0].created_at, "20240101_090000");
+}
+
+#[test]
+fn test_worktree_absolute_parent_dir() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    // Not created yet; create_worktree_with_config has to make it
+    let parent_dir = temp_dir.path().parent().unwrap().join("trees/nested");
+    let mut config = crate::default_worktree_config();
+    config.parent_dir = parent_dir.to_string_lossy().into_owned();
+
+    let result = create_worktree_with_config("5", &config);
+    let _ = std::env::set_current_dir(original_dir);
+
+    let worktree = result.unwrap();
+    assert_eq!(worktree.path, parent_dir.join(&worktree.name));
+    assert!(worktree.path.join("README.md").exists());
+}
+
+#[test]
+fn test_worktree_limit_enforced_on_creation() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Failed to get current directory: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = std::env::set_current_dir(temp_dir.path()) {
+        eprintln!("Failed to change to temp directory: {}", e);
+        return;
+    }
+
+    let mut config = crate::default_worktree_config();
+    config.max_worktrees = 2;
+
+    // Create up to the limit
+    create_worktree_with_config("limit-1", &config).unwrap();
+    create_worktree_with_config("limit-2", &config).unwrap();
+
+    let result = create_worktree_with_config("limit-3", &config);
+    assert!(matches!(result, Err(WorktreeError::LimitReached(2))));
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("--cleanup-worktrees"));
+    assert_eq!(list_worktrees_with_config(&config).unwrap().len(), 2);
+
+    // Cleanup
+    let _ = std::env::set_current_dir(original_dir);
+}
+
+#[test]
+fn test_parse_worktree_list_keeps_detached_entries() {
+    let porcelain = "worktree /repo\n\
+HEAD 1111111111111111111111111111111111111111\n\
+branch refs/heads/main\n\
+\n\
+worktree /claude-phase-2-20240101_090000\n\
+HEAD 2222222222222222222222222222222222222222\n\
+branch refs/heads/claude-phase-2-20240101_090000\n\
+\n\
+worktree /claude-phase-3-20240102_090000\n\
+HEAD abcdef0123456789abcdef0123456789abcdef01\n\
+detached\n\
+\n";
+
+    let worktrees = parse_worktree_list(porcelain, &WorktreeState::new());
+    assert_eq!(worktrees.len(), 3);
+    assert_eq!(worktrees[1].branch, "claude-phase-2-20240101_090000");
+
+    let detached = &worktrees[2];
+    assert_eq!(detached.name, "claude-phase-3-20240102_090000");
+    assert_eq!(detached.branch, "abcdef0");
+    assert_eq!(
+        detached.path,
+        std::path::PathBuf::from("/claude-phase-3-20240102_090000")
+    );
+}
+
+#[test]
+fn test_branch_matches_pattern() {
+    let pattern = "feat-{id}-{timestamp}";
+    assert!(branch_matches_pattern("feat-3-20250101_120000", pattern));
+    assert!(branch_matches_pattern(
+        "feat-3-20250101_120000-retry",
+        pattern
+    ));
+    assert!(!branch_matches_pattern("feat-3", pattern));
+    assert!(!branch_matches_pattern("main", pattern));
+    assert!(branch_matches_pattern(
+        "wt/7/20250101_120000",
+        "wt/{id}/{timestamp}"
+    ));
+
+    // {timestamp} only matches a real stamp, so loose patterns don't claim
+    // the user's own branches
+    assert!(!branch_matches_pattern("feat-3-notastamp", pattern));
+    assert!(!branch_matches_pattern("feature-x", "{id}-{timestamp}"));
+    assert!(branch_matches_pattern(
+        "2-20250101_120000",
+        "{id}-{timestamp}"
+    ));
+    assert!(!branch_matches_pattern("main", "{id}"));
+}
+
+#[test]
+fn test_validate_naming_pattern() {
+    assert!(validate_naming_pattern("claude-phase-{id}-{timestamp}").is_ok());
+    assert!(validate_naming_pattern("wt/{id}/{timestamp}").is_ok());
+
+    let error = validate_naming_pattern("claude-{phase}-{timestamp}")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("unknown token {phase}"), "{}", error);
+    assert!(error.contains("{id} and {timestamp}"), "{}", error);
+    assert!(validate_naming_pattern("claude-{id").is_err());
+
+    let error = validate_naming_pattern("claude phase {id} {timestamp}")
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("\"claude phase 1 20250101_120000\""),
+        "{}",
+        error
+    );
+    for pattern in [
+        "claude..{id}-{timestamp}",
+        "wt/{id}-{timestamp}.lock",
+        "-{id}-{timestamp}",
+        "wt/.{id}-{timestamp}",
+    ] {
+        let error = validate_naming_pattern(pattern).unwrap_err().to_string();
+        assert!(error.contains("which git rejects"), "{}", error);
+    }
+
+    // Patterns branch_matches_pattern couldn't tell from user branches
+    for pattern in ["{id}", "{id}-{timestamp}", "{timestamp}-claude"] {
+        let error = validate_naming_pattern(pattern).unwrap_err().to_string();
+        assert!(error.contains("must start with literal text"), "{}", error);
+    }
+    for pattern in ["claude-{id}", "fixed-name"] {
+        let error = validate_naming_pattern(pattern).unwrap_err().to_string();
+        assert!(error.contains("must contain {timestamp}"), "{}", error);
+    }
+}
+
+#[test]
+fn test_sync_worktree_safely_rebases_onto_origin() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    let run = |dir: &std::path::Path, args: &[&str]| {
+        let output = git_in(dir, args).unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+    let commit_file = |dir: &std::path::Path, file: &str, content: &str| {
+        fs::write(dir.join(file), content).unwrap();
+        run(dir, &["add", file]);
+        run(dir, &["commit", "-m", file]);
+    };
+
+    let origin = temp_dir.path().parent().unwrap().join("origin.git");
+    let origin = origin.to_str().unwrap();
+    run(temp_dir.path(), &["init", "--bare", origin]);
+    run(temp_dir.path(), &["remote", "add", "origin", origin]);
+    run(temp_dir.path(), &["push", "origin", "main"]);
+
+    // main and the phase branch diverge on different files
+    let worktree = create_worktree("sync-test", "main").unwrap();
+    commit_file(&worktree.path, "phase.txt", "phase work");
+    commit_file(temp_dir.path(), "main.txt", "main work");
+    run(temp_dir.path(), &["push", "origin", "main"]);
+
+    let result = sync_worktree_safely(&worktree, "main");
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(worktree.path.join("main.txt").exists());
+    assert!(worktree.path.join("phase.txt").exists());
+
+    // Both sides now edit the same file, so the rebase is aborted
+    commit_file(&worktree.path, "README.md", "phase readme");
+    commit_file(temp_dir.path(), "README.md", "main readme");
+    run(temp_dir.path(), &["push", "origin", "main"]);
+
+    let error = sync_worktree_safely(&worktree, "main")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("rebase aborted"), "{}", error);
+    assert!(error.contains("README.md"), "{}", error);
+    let rebase_head = git_in(
+        &worktree.path,
+        &["rev-parse", "-q", "--verify", "REBASE_HEAD"],
+    );
+    assert!(!rebase_head.unwrap().status.success());
+    assert_eq!(
+        fs::read_to_string(worktree.path.join("README.md")).unwrap(),
+        "phase readme"
+    );
+
+    let _ = std::env::set_current_dir(original_dir);
+}
+
 #[test]
 fn test_worktree_removal() {
+    let _cwd = crate::lock_cwd();
     let Some(temp_dir) = setup_test_repo() else {
         return; // Skip test if git is not available
     };
@@ -207,6 +557,7 @@ fn test_worktree_removal() {
 
 #[test]
 fn test_worktree_state_management() {
+    let _cwd = crate::lock_cwd();
     let temp_dir = TempDir::new().unwrap();
     let original_dir = match std::env::current_dir() {
         Ok(dir) => dir,
@@ -278,8 +629,77 @@ fn test_worktree_state_management() {
     let _ = std::env::set_current_dir(original_dir);
 }
 
+#[test]
+fn test_prune_worktree_removed_out_of_band() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    fs::create_dir(".claude-launcher").unwrap();
+
+    let kept = create_worktree("prune-1", "main").unwrap();
+    let removed = create_worktree("prune-2", "main").unwrap();
+    let mut state = WorktreeState::new();
+    state.add_worktree("1".to_string(), &kept);
+    state.add_worktree("2".to_string(), &removed);
+    state.save().unwrap();
+
+    fs::remove_dir_all(&removed.path).unwrap();
+
+    let pruned = recover_orphaned_worktrees().unwrap();
+    let mut state = WorktreeState::load().unwrap();
+    let missing = state.prune_missing();
+    state.save().unwrap();
+    let reloaded = WorktreeState::load().unwrap();
+    let _ = std::env::set_current_dir(original_dir);
+
+    assert_eq!(pruned, vec![removed.name.clone()]);
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].worktree_name, removed.name);
+    assert_eq!(reloaded.active_worktrees.len(), 1);
+    assert_eq!(reloaded.active_worktrees[0].worktree_name, kept.name);
+}
+
+#[test]
+fn test_state_mismatches_report_orphaned_and_untracked() {
+    let temp_dir = TempDir::new().unwrap();
+    let on_disk_path = temp_dir.path().join("claude-phase-2-20240102_090000");
+    fs::create_dir(&on_disk_path).unwrap();
+
+    let mut state = WorktreeState::new();
+    let gone = Worktree {
+        name: "claude-phase-1-20240101_090000".to_string(),
+        path: temp_dir.path().join("claude-phase-1-20240101_090000"),
+        branch: "claude-phase-1-20240101_090000".to_string(),
+        created_at: "20240101_090000".to_string(),
+    };
+    state.add_worktree("1".to_string(), &gone);
+    let on_disk = Worktree {
+        name: "claude-phase-2-20240102_090000".to_string(),
+        path: on_disk_path.clone(),
+        branch: "claude-phase-2-20240102_090000".to_string(),
+        created_at: "20240102_090000".to_string(),
+    };
+
+    let mismatches = state.mismatches(&[on_disk]);
+    assert_eq!(mismatches.orphaned.len(), 1);
+    assert_eq!(mismatches.orphaned[0].worktree_name, gone.name);
+    assert_eq!(mismatches.untracked.len(), 1);
+    assert_eq!(mismatches.untracked[0].path, on_disk_path);
+
+    // Once the state knows about it and the orphan is gone, nothing is flagged
+    let mut state = WorktreeState::new();
+    state.add_worktree("2".to_string(), &mismatches.untracked[0]);
+    let mismatches = state.mismatches(&mismatches.untracked);
+    assert!(mismatches.orphaned.is_empty());
+    assert!(mismatches.untracked.is_empty());
+}
+
 #[test]
 fn test_cleanup_old_worktrees() {
+    let _cwd = crate::lock_cwd();
     let Some(temp_dir) = setup_test_repo() else {
         return; // Skip test if git is not available
     };
@@ -361,6 +781,7 @@ fn test_get_active_worktree() {
 
 #[test]
 fn test_worktree_creation_with_invalid_branch() {
+    let _cwd = crate::lock_cwd();
     let Some(temp_dir) = setup_test_repo() else {
         return; // Skip test if git is not available
     };
@@ -387,6 +808,7 @@ fn test_worktree_creation_with_invalid_branch() {
 
 #[test]
 fn test_get_current_branch() {
+    let _cwd = crate::lock_cwd();
     if !check_git_available() {
         return; // Skip test if git is not available
     }
@@ -416,3 +838,46 @@ fn test_get_current_branch() {
     // Cleanup
     let _ = std::env::set_current_dir(original_dir);
 }
+
+#[test]
+fn test_default_branch_detection() {
+    let _cwd = crate::lock_cwd();
+    let Some(temp_dir) = setup_test_repo() else {
+        return; // Skip test if git is not available
+    };
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = git(args).unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    // Without a remote the checked-out branch is used
+    run(&["branch", "-m", "main", "master"]);
+    assert_eq!(default_branch(), "master");
+
+    // Once origin has a default branch it wins over the current one
+    let origin = temp_dir.path().parent().unwrap().join("origin.git");
+    let origin = origin.to_str().unwrap();
+    run(&["init", "--bare", origin]);
+    run(&["remote", "add", "origin", origin]);
+    run(&["push", "origin", "master"]);
+    run(&["remote", "set-head", "origin", "master"]);
+    run(&["checkout", "-b", "feature"]);
+    assert_eq!(default_branch(), "master");
+
+    // A config without base_branch creates worktrees from master
+    let config = crate::default_worktree_config();
+    assert_eq!(config.base_branch, None);
+    let worktree = create_worktree_with_config("default-branch", &config).unwrap();
+    let base = git(&["merge-base", "--is-ancestor", "master", &worktree.branch]).unwrap();
+    assert!(base.status.success());
+
+    let _ = std::env::set_current_dir(original_dir);
+}
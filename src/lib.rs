@@ -1,29 +1,360 @@
+use serde::{Deserialize, Serialize};
+
+/// Terminal application that agent tabs are opened in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum TerminalBackend {
+    #[default]
+    #[serde(rename = "iterm")]
+    ITerm,
+    #[serde(rename = "terminal", alias = "terminal.app")]
+    TerminalApp,
+    #[serde(rename = "tmux")]
+    Tmux,
+    /// Windows Terminal, driven through `wt.exe` with a cmd.exe command line.
+    #[serde(rename = "windows-terminal")]
+    WindowsTerminal,
+}
+
+/// Whether each iTerm agent after the first opens in a new tab or splits the
+/// current one into panes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalLayout {
+    #[default]
+    Tab,
+    Pane,
+}
+
+/// Where agents are opened. `profile` names the iTerm profile new sessions
+/// use (the default profile when unset); `layout` only applies to iTerm.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TerminalConfig {
+    #[serde(default)]
+    pub backend: TerminalBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub layout: TerminalLayout,
+}
+
+/// Tmux session created when no tmux server is running yet.
+pub const TMUX_SESSION: &str = "claude-launcher";
+
+/// The agent CLI each tab runs, e.g. `claude --dangerously-skip-permissions`.
+/// A `command_template` such as `aider --message-file {prompt}` replaces the
+/// default `<binary> <args> < {prompt}` form. A `model` is passed as
+/// `--model <name>` right after the binary. With `keep_prompt` the prompt
+/// file is left in place after the agent exits. `max_retries` above 1 runs
+/// the agent again, up to that many times in all, until it exits cleanly.
+/// `env` variables are exported in the tab before the agent starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentCommand {
+    pub binary: String,
+    pub extra_args: Vec<String>,
+    pub command_template: Option<String>,
+    pub model: Option<String>,
+    pub keep_prompt: bool,
+    pub max_retries: u32,
+    pub env: Vec<(String, String)>,
+}
+
+impl Default for AgentCommand {
+    fn default() -> Self {
+        AgentCommand {
+            binary: "claude".to_string(),
+            extra_args: vec!["--dangerously-skip-permissions".to_string()],
+            command_template: None,
+            model: None,
+            keep_prompt: false,
+            max_retries: 1,
+            env: Vec::new(),
+        }
+    }
+}
+
+impl AgentCommand {
+    /// Binary followed by its arguments, quoted only where the shell needs it.
+    pub fn invocation(&self) -> String {
+        self.invocation_with(shell_word)
+    }
+
+    /// The command template, with `{prompt}` and optional `{dir}` placeholders.
+    pub fn template(&self) -> String {
+        self.template_with(shell_word)
+    }
+
+    fn invocation_with(&self, word: fn(&str) -> String) -> String {
+        let model = self
+            .model
+            .as_ref()
+            .map(|model| ["--model".to_string(), model.clone()]);
+        std::iter::once(&self.binary)
+            .chain(model.iter().flatten())
+            .chain(&self.extra_args)
+            .map(|value| word(value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn template_with(&self, word: fn(&str) -> String) -> String {
+        match &self.command_template {
+            Some(template) => template.clone(),
+            None => format!("{} < {{prompt}}", self.invocation_with(word)),
+        }
+    }
+}
+
+/// Shell command run for each agent: feed the prompt file to the agent, then
+/// delete it unless `keep_prompt` is set.
+pub fn agent_shell_command(current_dir: &str, prompt_file: &str, agent: &AgentCommand) -> String {
+    // Use the exact same pattern as parallel-agent-automation, with paths
+    // quoted so directories containing spaces still work
+    let prompt_file = shell_quote(prompt_file);
+    let current_dir = shell_quote(current_dir);
+    let mut agent_command = agent
+        .template()
+        .replace("{prompt}", &prompt_file)
+        .replace("{dir}", &current_dir);
+    if agent.max_retries > 1 {
+        // The last failed attempt skips the sleep, so the loop (and the
+        // `&& rm`) fails like a single failed run would
+        agent_command = format!(
+            "for i in $(seq 1 {n}); do {} && break; [ $i -lt {n} ] && sleep 5; done",
+            agent_command,
+            n = agent.max_retries
+        );
+    }
+    if !agent.env.is_empty() {
+        let vars: Vec<String> = agent
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, shell_word(value)))
+            .collect();
+        agent_command = format!("export {} && {}", vars.join(" "), agent_command);
+    }
+    let command = format!("cd {} && {}", current_dir, agent_command);
+    if agent.keep_prompt {
+        command
+    } else {
+        format!("{} && rm {}", command, prompt_file)
+    }
+}
+
+/// cmd.exe equivalent of `agent_shell_command` for Windows Terminal. `&`
+/// rather than `&&` deletes the prompt file however the agent exits.
+pub fn windows_shell_command(current_dir: &str, prompt_file: &str, agent: &AgentCommand) -> String {
+    // Prompt paths are built with '/', which cmd.exe would read as a switch
+    let prompt_file = cmd_quote(&prompt_file.replace('/', "\\"));
+    let current_dir = cmd_quote(&current_dir.replace('/', "\\"));
+    let mut agent_command = agent
+        .template_with(cmd_word)
+        .replace("{prompt}", &prompt_file)
+        .replace("{dir}", &current_dir);
+    for (key, value) in agent.env.iter().rev() {
+        agent_command = format!("set \"{}={}\" && {}", key, value, agent_command);
+    }
+    let command = format!("cd /d {} && {}", current_dir, agent_command);
+    if agent.keep_prompt {
+        command
+    } else {
+        format!("{} & del {}", command, prompt_file)
+    }
+}
+
+/// Arguments for `wt.exe` that open a titled tab running the agent.
+pub fn generate_windows_terminal_command(
+    task: &str,
+    current_dir: &str,
+    prompt_file: &str,
+    agent: &AgentCommand,
+) -> Vec<String> {
+    let title = task.split(':').next().unwrap_or(task).trim();
+    vec![
+        "new-tab".to_string(),
+        "--title".to_string(),
+        title.to_string(),
+        "cmd".to_string(),
+        "/c".to_string(),
+        windows_shell_command(current_dir, prompt_file, agent),
+    ]
+}
+
 pub fn generate_applescript(
     _task: &str,
     current_dir: &str,
     prompt_file: &str,
-    _is_first: bool,
+    is_first: bool,
+    terminal: &TerminalConfig,
+    agent: &AgentCommand,
 ) -> String {
-    // Use the exact same pattern as parallel-agent-automation
-    let shell_command = format!(
-        "cd {} && claude --dangerously-skip-permissions < {} && rm {}",
-        current_dir, prompt_file, prompt_file
-    );
-
-    // Both first and additional tabs use the same AppleScript
-    format!(
-        r#"tell application "iTerm"
+    // The command is embedded in an AppleScript string literal
+    let shell_command = applescript_escape(&agent_shell_command(current_dir, prompt_file, agent));
+    let profile = iterm_profile(terminal.profile.as_deref());
+
+    match terminal.backend {
+        // With the pane layout, the first agent opens a tab and the others
+        // split the session opened before them
+        TerminalBackend::ITerm if terminal.layout == TerminalLayout::Pane && !is_first => {
+            format!(
+                r#"tell application "iTerm"
+    tell current session of current window
+        set agentSession to (split vertically with {})
+    end tell
+    tell agentSession
+        write text "{}"
+    end tell
+end tell"#,
+                profile, shell_command
+            )
+        }
+        // Otherwise first and additional tabs use the same AppleScript. tmux
+        // and Windows Terminal are driven directly (see `generate_tmux_command`
+        // and `generate_windows_terminal_command`), so they fall back here.
+        TerminalBackend::ITerm | TerminalBackend::Tmux | TerminalBackend::WindowsTerminal => {
+            format!(
+                r#"tell application "iTerm"
     tell current window
-        create tab with default profile
+        create tab with {}
         tell current session
             write text "{}"
         end tell
     end tell
 end tell"#,
-        shell_command
+                profile, shell_command
+            )
+        }
+        // Terminal.app has no tab scripting; `do script` opens a new window
+        TerminalBackend::TerminalApp => format!(
+            r#"tell application "Terminal"
+    activate
+    do script "{}"
+end tell"#,
+            shell_command
+        ),
+    }
+}
+
+/// The profile clause of an iTerm `create tab`: a named profile such as
+/// `profile "AI Agent"`, or `default profile` when none is configured.
+pub fn iterm_profile(profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("profile \"{}\"", applescript_escape(name)),
+        None => "default profile".to_string(),
+    }
+}
+
+/// Fills `{cmd}` in a user-defined terminal launch template such as
+/// `alacritty -e bash -c '{cmd}'`. The result is run with `sh -c`. `{cmd}`
+/// sits inside single quotes, so any `'` in the command becomes `'\''`.
+pub fn render_launch_template(template: &str, shell_command: &str) -> String {
+    template.replace("{cmd}", &shell_command.replace('\'', r"'\''"))
+}
+
+/// Arguments for `tmux` that run `shell_command` in a new window. Without a
+/// running server a detached session is started to hold the window.
+pub fn tmux_args(window_name: &str, shell_command: &str, server_running: bool) -> Vec<String> {
+    let mut args: Vec<String> = if server_running {
+        vec!["new-window".to_string()]
+    } else {
+        vec![
+            "new-session".to_string(),
+            "-d".to_string(),
+            "-s".to_string(),
+            TMUX_SESSION.to_string(),
+        ]
+    };
+    args.push("-n".to_string());
+    args.push(window_name.to_string());
+    args.push(shell_command.to_string());
+    args
+}
+
+pub fn generate_tmux_command(
+    task: &str,
+    current_dir: &str,
+    prompt_file: &str,
+    server_running: bool,
+    agent: &AgentCommand,
+) -> Vec<String> {
+    tmux_args(
+        &tmux_window_name(task),
+        &agent_shell_command(current_dir, prompt_file, agent),
+        server_running,
     )
 }
 
+/// Short window name from a task, e.g. "Phase 1, Step 1A: ..." -> "phase-1-step-1a".
+pub fn tmux_window_name(task: &str) -> String {
+    let head = task.split(':').next().unwrap_or(task);
+    let mut name = String::new();
+    for c in head.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name: String = name.trim_end_matches('-').chars().take(30).collect();
+    if name.is_empty() {
+        "claude".to_string()
+    } else {
+        name
+    }
+}
+
+/// Escapes a value for use inside an AppleScript double-quoted string.
+pub fn applescript_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Leaves plain words (flags, paths without spaces) as they are and quotes the rest.
+fn shell_word(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        shell_quote(value)
+    }
+}
+
+/// Like `shell_word`, but quoted for cmd.exe.
+fn cmd_word(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./\\=:@+,".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        cmd_quote(value)
+    }
+}
+
+/// Wraps a value in double quotes for cmd.exe. Windows paths cannot contain
+/// `"`, so there is nothing to escape.
+fn cmd_quote(value: &str) -> String {
+    format!("\"{}\"", value)
+}
+
+/// Wraps a value in double quotes for a POSIX shell, escaping the characters
+/// that keep their special meaning inside double quotes.
+pub fn shell_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,11 +366,13 @@ mod tests {
             "/test/dir",
             "/test/dir/agent_prompt_task_1.txt",
             true,
+            &TerminalConfig::default(),
+            &AgentCommand::default(),
         );
 
         assert!(script.contains("tell application \"iTerm\""));
         assert!(script.contains("create tab with default profile"));
-        assert!(script.contains("cd /test/dir && claude --dangerously-skip-permissions < /test/dir/agent_prompt_task_1.txt"));
+        assert!(script.contains(r#"cd \"/test/dir\" && claude --dangerously-skip-permissions < \"/test/dir/agent_prompt_task_1.txt\""#));
     }
 
     #[test]
@@ -49,12 +382,61 @@ mod tests {
             "/test/dir",
             "/test/dir/agent_prompt_task_2.txt",
             false,
+            &TerminalConfig::default(),
+            &AgentCommand::default(),
         );
 
         assert!(script.contains("tell application \"iTerm\""));
         assert!(script.contains("create tab with default profile"));
-        assert!(script
-            .contains("claude --dangerously-skip-permissions < /test/dir/agent_prompt_task_2.txt"));
+        assert!(script.contains(
+            r#"claude --dangerously-skip-permissions < \"/test/dir/agent_prompt_task_2.txt\""#
+        ));
+    }
+
+    #[test]
+    fn test_generate_applescript_named_profile() {
+        let script = generate_applescript(
+            "test task",
+            "/test/dir",
+            "/test/dir/agent_prompt_task_1.txt",
+            true,
+            &TerminalConfig {
+                profile: Some("AI \"Agent\"".to_string()),
+                ..TerminalConfig::default()
+            },
+            &AgentCommand::default(),
+        );
+
+        assert!(script.contains(r#"create tab with profile "AI \"Agent\"""#));
+        assert!(!script.contains("default profile"));
+    }
+
+    #[test]
+    fn test_generate_applescript_pane_layout() {
+        let terminal = TerminalConfig {
+            layout: TerminalLayout::Pane,
+            ..TerminalConfig::default()
+        };
+        let script = |is_first| {
+            generate_applescript(
+                "test task",
+                "/test/dir",
+                "/test/dir/agent_prompt_task_1.txt",
+                is_first,
+                &terminal,
+                &AgentCommand::default(),
+            )
+        };
+
+        // The first agent still opens the tab the others split
+        assert!(script(true).contains("create tab with default profile"));
+        assert!(!script(true).contains("split"));
+
+        let pane = script(false);
+        assert!(!pane.contains("create tab"));
+        assert!(pane.contains("tell current session of current window"));
+        assert!(pane.contains("set agentSession to (split vertically with default profile)"));
+        assert!(pane.contains(r#"write text "cd \"/test/dir\" && claude"#));
     }
 
     #[test]
@@ -64,8 +446,337 @@ mod tests {
             "/work/dir",
             "/work/dir/agent_prompt_task_1.txt",
             true,
+            &TerminalConfig::default(),
+            &AgentCommand::default(),
+        );
+
+        assert!(script.contains(r#"cd \"/work/dir\" && claude --dangerously-skip-permissions < \"/work/dir/agent_prompt_task_1.txt\" && rm \"/work/dir/agent_prompt_task_1.txt\""#));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("3001"), "\"3001\"");
+        assert_eq!(
+            shell_quote("a \"b\" $HOME `x` \\"),
+            "\"a \\\"b\\\" \\$HOME \\`x\\` \\\\\""
+        );
+    }
+
+    #[test]
+    fn test_generate_applescript_terminal_app() {
+        let script = generate_applescript(
+            "test task",
+            "/test/dir",
+            "/test/dir/agent_prompt_task_1.txt",
+            true,
+            &TerminalConfig {
+                backend: TerminalBackend::TerminalApp,
+                ..TerminalConfig::default()
+            },
+            &AgentCommand::default(),
+        );
+
+        assert!(script.contains("tell application \"Terminal\""));
+        assert!(script.contains(r#"do script "cd \"/test/dir\" && claude --dangerously-skip-permissions < \"/test/dir/agent_prompt_task_1.txt\" && rm \"/test/dir/agent_prompt_task_1.txt\"""#));
+        assert!(!script.contains("iTerm"));
+    }
+
+    #[test]
+    fn test_terminal_backend_config_values() {
+        let backend: TerminalBackend = serde_json::from_str("\"terminal\"").unwrap();
+        assert_eq!(backend, TerminalBackend::TerminalApp);
+        let backend: TerminalBackend = serde_json::from_str("\"iterm\"").unwrap();
+        assert_eq!(backend, TerminalBackend::ITerm);
+        assert_eq!(TerminalBackend::default(), TerminalBackend::ITerm);
+    }
+
+    #[test]
+    fn test_generate_tmux_command() {
+        let args = generate_tmux_command(
+            "Phase 1, Step 1A: Create schema",
+            "/test/dir",
+            "/test/dir/agent_prompt_task_1.txt",
+            true,
+            &AgentCommand::default(),
+        );
+        assert_eq!(
+            args,
+            vec![
+                "new-window",
+                "-n",
+                "phase-1-step-1a",
+                r#"cd "/test/dir" && claude --dangerously-skip-permissions < "/test/dir/agent_prompt_task_1.txt" && rm "/test/dir/agent_prompt_task_1.txt""#,
+            ]
+        );
+
+        // No server yet: start a detached session holding the window
+        let args = generate_tmux_command(
+            "Smart Init",
+            "/test/dir",
+            "/tmp/p.txt",
+            false,
+            &AgentCommand::default(),
+        );
+        assert_eq!(
+            &args[..6],
+            &["new-session", "-d", "-s", TMUX_SESSION, "-n", "smart-init"]
+        );
+    }
+
+    #[test]
+    fn test_generate_applescript_quotes_paths() {
+        let script = generate_applescript(
+            "test",
+            "/Users/me/My Projects/app",
+            "/Users/me/My Projects/app/it's \"quoted\".txt",
+            true,
+            &TerminalConfig::default(),
+            &AgentCommand::default(),
+        );
+
+        // Shell level: cd "/Users/me/My Projects/app" ... < "...it's \"quoted\".txt"
+        // AppleScript level: every quote and backslash of that is escaped again
+        assert!(script.contains(r#"write text "cd \"/Users/me/My Projects/app\" && claude"#));
+        assert!(script.contains(r#"< \"/Users/me/My Projects/app/it's \\\"quoted\\\".txt\""#));
+
+        // The string literal must not be closed early by an unescaped quote
+        let line = script
+            .lines()
+            .find(|l| l.contains("write text"))
+            .unwrap()
+            .trim();
+        let literal = &line["write text \"".len()..line.len() - 1];
+        let mut escaped = false;
+        for c in literal.chars() {
+            assert!(escaped || c != '"', "unescaped quote in {}", literal);
+            escaped = !escaped && c == '\\';
+        }
+    }
+
+    #[test]
+    fn test_applescript_escape() {
+        assert_eq!(applescript_escape(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+    }
+
+    #[test]
+    fn test_custom_agent_binary() {
+        let agent = AgentCommand {
+            binary: "/opt/ai tools/claude-dev".to_string(),
+            extra_args: vec!["--model".to_string(), "fast".to_string()],
+            command_template: None,
+            model: None,
+            keep_prompt: false,
+            max_retries: 1,
+            env: Vec::new(),
+        };
+        let command = agent_shell_command("/work/dir", "/work/dir/p.txt", &agent);
+        assert_eq!(
+            command,
+            r#"cd "/work/dir" && "/opt/ai tools/claude-dev" --model fast < "/work/dir/p.txt" && rm "/work/dir/p.txt""#
+        );
+    }
+
+    #[test]
+    fn test_agent_without_extra_args() {
+        let agent = AgentCommand {
+            binary: "claude".to_string(),
+            extra_args: vec![],
+            command_template: None,
+            model: None,
+            keep_prompt: false,
+            max_retries: 1,
+            env: Vec::new(),
+        };
+        let script = generate_applescript(
+            "test",
+            "/work/dir",
+            "/work/dir/p.txt",
+            true,
+            &TerminalConfig::default(),
+            &agent,
+        );
+        assert!(script.contains(r#"&& claude < \"/work/dir/p.txt\""#));
+    }
+
+    #[test]
+    fn test_agent_command_template() {
+        let agent = AgentCommand {
+            command_template: Some(
+                "aider --message-file {prompt} --subtree-only {dir}".to_string(),
+            ),
+            ..AgentCommand::default()
+        };
+        let command = agent_shell_command("/my app", "/my app/p.txt", &agent);
+        assert_eq!(
+            command,
+            r#"cd "/my app" && aider --message-file "/my app/p.txt" --subtree-only "/my app" && rm "/my app/p.txt""#
+        );
+
+        // The default template reproduces the claude command
+        assert_eq!(
+            AgentCommand::default().template(),
+            "claude --dangerously-skip-permissions < {prompt}"
+        );
+    }
+
+    #[test]
+    fn test_windows_shell_command() {
+        let command = windows_shell_command(
+            r"C:\Users\me\My Projects\app",
+            r"C:\Users\me\My Projects\app/agent_prompt_task_1.txt",
+            &AgentCommand::default(),
+        );
+        assert_eq!(
+            command,
+            r#"cd /d "C:\Users\me\My Projects\app" && claude --dangerously-skip-permissions < "C:\Users\me\My Projects\app\agent_prompt_task_1.txt" & del "C:\Users\me\My Projects\app\agent_prompt_task_1.txt""#
+        );
+
+        // Backslashes in the binary path are left alone, unlike POSIX quoting
+        let agent = AgentCommand {
+            binary: r"C:\Program Files\Claude\claude.exe".to_string(),
+            extra_args: vec![],
+            command_template: None,
+            model: None,
+            keep_prompt: false,
+            max_retries: 1,
+            env: Vec::new(),
+        };
+        let command = windows_shell_command(r"C:\app", r"C:\app\p.txt", &agent);
+        assert_eq!(
+            command,
+            r#"cd /d "C:\app" && "C:\Program Files\Claude\claude.exe" < "C:\app\p.txt" & del "C:\app\p.txt""#
+        );
+    }
+
+    #[test]
+    fn test_agent_env_is_exported() {
+        let agent = AgentCommand {
+            env: vec![
+                ("DATABASE_URL".to_string(), "postgres://db/app".to_string()),
+                ("GREETING".to_string(), r#"it's "$HOME""#.to_string()),
+            ],
+            ..AgentCommand::default()
+        };
+        let command = agent_shell_command("/work/dir", "/work/dir/p.txt", &agent);
+        assert_eq!(
+            command,
+            r#"cd "/work/dir" && export DATABASE_URL=postgres://db/app GREETING="it's \"\$HOME\"" && claude --dangerously-skip-permissions < "/work/dir/p.txt" && rm "/work/dir/p.txt""#
+        );
+
+        // Still escaped once embedded in AppleScript
+        let script = generate_applescript(
+            "test",
+            "/work/dir",
+            "/work/dir/p.txt",
+            true,
+            &TerminalConfig::default(),
+            &agent,
+        );
+        assert!(script.contains(r#"GREETING=\"it's \\\"\\$HOME\\\"\" && claude"#));
+
+        let command = windows_shell_command(r"C:\app", r"C:\app\p.txt", &agent);
+        assert!(command.starts_with(
+            r#"cd /d "C:\app" && set "DATABASE_URL=postgres://db/app" && set "GREETING="#
+        ));
+    }
+
+    #[test]
+    fn test_agent_retry_loop() {
+        let mut agent = AgentCommand::default();
+        let command = agent_shell_command("/work/dir", "/work/dir/p.txt", &agent);
+        assert!(!command.contains("for i in"), "{}", command);
+
+        agent.max_retries = 3;
+        assert_eq!(
+            agent_shell_command("/work/dir", "/work/dir/p.txt", &agent),
+            r#"cd "/work/dir" && for i in $(seq 1 3); do claude --dangerously-skip-permissions < "/work/dir/p.txt" && break; [ $i -lt 3 ] && sleep 5; done && rm "/work/dir/p.txt""#
+        );
+
+        // The loop survives the AppleScript string escaping
+        let script = generate_applescript(
+            "test",
+            "/work/dir",
+            "/work/dir/p.txt",
+            true,
+            &TerminalConfig::default(),
+            &agent,
+        );
+        assert!(script.contains(r#"for i in $(seq 1 3); do claude"#));
+    }
+
+    #[test]
+    fn test_keep_prompt_drops_cleanup() {
+        let mut agent = AgentCommand::default();
+        let command = agent_shell_command("/work/dir", "/work/dir/p.txt", &agent);
+        assert!(command.ends_with(r#" && rm "/work/dir/p.txt""#));
+        let command = windows_shell_command(r"C:\app", r"C:\app\p.txt", &agent);
+        assert!(command.ends_with(r#" & del "C:\app\p.txt""#));
+
+        agent.keep_prompt = true;
+        assert_eq!(
+            agent_shell_command("/work/dir", "/work/dir/p.txt", &agent),
+            r#"cd "/work/dir" && claude --dangerously-skip-permissions < "/work/dir/p.txt""#
+        );
+        assert_eq!(
+            windows_shell_command(r"C:\app", r"C:\app\p.txt", &agent),
+            r#"cd /d "C:\app" && claude --dangerously-skip-permissions < "C:\app\p.txt""#
+        );
+    }
+
+    #[test]
+    fn test_generate_windows_terminal_command() {
+        let args = generate_windows_terminal_command(
+            "Phase 1, Step 1A: Create schema",
+            r"C:\app",
+            r"C:\app\agent_prompt_task_1.txt",
+            &AgentCommand::default(),
+        );
+        assert_eq!(
+            args,
+            vec![
+                "new-tab",
+                "--title",
+                "Phase 1, Step 1A",
+                "cmd",
+                "/c",
+                r#"cd /d "C:\app" && claude --dangerously-skip-permissions < "C:\app\agent_prompt_task_1.txt" & del "C:\app\agent_prompt_task_1.txt""#,
+            ]
+        );
+
+        let backend: TerminalBackend = serde_json::from_str("\"windows-terminal\"").unwrap();
+        assert_eq!(backend, TerminalBackend::WindowsTerminal);
+    }
+
+    #[test]
+    fn test_render_launch_template() {
+        let shell_command = agent_shell_command(
+            "/test/dir",
+            "/test/dir/agent_prompt_task_1.txt",
+            &AgentCommand::default(),
+        );
+        assert_eq!(
+            render_launch_template("alacritty -e bash -c '{cmd}'", &shell_command),
+            r#"alacritty -e bash -c 'cd "/test/dir" && claude --dangerously-skip-permissions < "/test/dir/agent_prompt_task_1.txt" && rm "/test/dir/agent_prompt_task_1.txt"'"#
+        );
+        assert_eq!(
+            render_launch_template("kitty @ launch --type=tab sh -c '{cmd}'", "true"),
+            "kitty @ launch --type=tab sh -c 'true'"
         );
 
-        assert!(script.contains("cd /work/dir && claude --dangerously-skip-permissions < /work/dir/agent_prompt_task_1.txt && rm /work/dir/agent_prompt_task_1.txt"));
+        // A quote in the project path doesn't end the single-quoted argument
+        let rendered = render_launch_template("bash -c '{cmd}'", r#"cd "/tmp/Bob's app" && pwd"#);
+        assert_eq!(rendered, r#"bash -c 'cd "/tmp/Bob'\''s app" && pwd'"#);
+        #[cfg(unix)]
+        {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(render_launch_template(
+                    "sh -c '{cmd}'",
+                    r#"echo "Bob's app""#,
+                ))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "Bob's app\n");
+        }
     }
 }